@@ -12,6 +12,108 @@ use turso::Value;
 #[cfg(target_os = "linux")]
 use crate::fuse::FuseMountOptions;
 
+/// Requested FUSE protocol features negotiated at mount time.
+///
+/// These are advertised in the INIT reply by [`crate::fuse::mount`] and
+/// intersected with what the kernel offers. Only `min_abi` is enforced: a
+/// kernel ABI below it fails the mount (signaled back through the `daemonize`
+/// pipe). A requested capability the kernel lacks is dropped from the
+/// negotiated set rather than failing the mount; no capability can be marked
+/// as required. The negotiated result is reported over the management control
+/// socket.
+#[derive(Debug, Clone)]
+pub struct FuseFeatures {
+    /// Enable writeback caching (batches small writes in the page cache).
+    pub writeback_cache: bool,
+    /// Use splice for read/write to avoid an extra copy through userspace.
+    pub splice_read: bool,
+    pub splice_write: bool,
+    /// Return attributes alongside directory entries (fewer lookups).
+    pub readdirplus: bool,
+    /// Allow concurrent operations within a single directory.
+    pub parallel_dirops: bool,
+    /// Maximum readahead the kernel may request, in bytes (`None` = default).
+    pub max_readahead: Option<u32>,
+    /// Maximum size of a single write, in bytes (`None` = default).
+    pub max_write: Option<u32>,
+    /// Minimum FUSE ABI version the mount requires, as `(major, minor)`.
+    /// The mount fails if the kernel negotiates a lower version.
+    pub min_abi: Option<(u32, u32)>,
+}
+
+impl Default for FuseFeatures {
+    fn default() -> Self {
+        // Conservative defaults that match the historical hardcoded behavior:
+        // nothing opt-in is requested and no minimum ABI is enforced.
+        FuseFeatures {
+            writeback_cache: false,
+            splice_read: false,
+            splice_write: false,
+            readdirplus: false,
+            parallel_dirops: false,
+            max_readahead: None,
+            max_write: None,
+            min_abi: None,
+        }
+    }
+}
+
+/// What the kernel actually offered in the FUSE INIT reply.
+///
+/// `crate::fuse::mount` builds this from the negotiated INIT response (the
+/// `capable` flags and the kernel-chosen limits / ABI) and passes it to
+/// [`FuseFeatures::negotiate`], whose JSON result is stored in the shared
+/// `negotiated` cell so the control socket can report what is truly live.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone)]
+pub struct KernelCaps {
+    pub writeback_cache: bool,
+    pub splice_read: bool,
+    pub splice_write: bool,
+    pub readdirplus: bool,
+    pub parallel_dirops: bool,
+    pub max_readahead: u32,
+    pub max_write: u32,
+    pub abi: (u32, u32),
+}
+
+#[cfg(target_os = "linux")]
+impl FuseFeatures {
+    /// Intersect the requested feature set with what the kernel offered.
+    ///
+    /// A requested boolean capability survives only if the kernel is also
+    /// `capable` of it; the size limits are clamped down to the kernel's
+    /// chosen maximum. If a `min_abi` was required and the kernel negotiated a
+    /// lower version the mount is rejected, matching the "fail the mount when a
+    /// required feature is unavailable" contract. The returned value is the
+    /// enabled set, shaped like `requested_features` so `describe` can present
+    /// the two side by side.
+    pub fn negotiate(&self, kernel: &KernelCaps) -> Result<serde_json::Value> {
+        if let Some((maj, min)) = self.min_abi {
+            if kernel.abi < (maj, min) {
+                anyhow::bail!(
+                    "kernel FUSE ABI {}.{} is below the required minimum {}.{}",
+                    kernel.abi.0,
+                    kernel.abi.1,
+                    maj,
+                    min
+                );
+            }
+        }
+
+        Ok(serde_json::json!({
+            "writeback_cache": self.writeback_cache && kernel.writeback_cache,
+            "splice_read": self.splice_read && kernel.splice_read,
+            "splice_write": self.splice_write && kernel.splice_write,
+            "readdirplus": self.readdirplus && kernel.readdirplus,
+            "parallel_dirops": self.parallel_dirops && kernel.parallel_dirops,
+            "max_readahead": self.max_readahead.map(|v| v.min(kernel.max_readahead)),
+            "max_write": self.max_write.map(|v| v.min(kernel.max_write)),
+            "abi": format!("{}.{}", kernel.abi.0, kernel.abi.1),
+        }))
+    }
+}
+
 /// Arguments for the mount command.
 #[derive(Debug, Clone)]
 pub struct MountArgs {
@@ -29,6 +131,13 @@ pub struct MountArgs {
     pub uid: Option<u32>,
     /// Group ID to report for all files (defaults to current group).
     pub gid: Option<u32>,
+    /// Mount inside a private mount namespace so the mount is only visible to
+    /// the agent's process subtree and does not propagate to the host.
+    pub private_namespace: bool,
+    /// Path to write the daemon's PID file to (enables `stop`).
+    pub pid_file: Option<PathBuf>,
+    /// FUSE protocol features to negotiate with the kernel.
+    pub features: FuseFeatures,
 }
 
 /// Mount the agent filesystem using FUSE.
@@ -45,14 +154,34 @@ pub fn mount(args: MountArgs) -> Result<()> {
     }
 
     let mountpoint = args.mountpoint.clone();
+    let control_id = args.id_or_path.clone();
+
+    // Snapshot the identity the control socket reports; the builder below
+    // moves the originals into `FuseMountOptions`.
+    let control_mountpoint = args.mountpoint.clone();
+    let control_fsname = fsname.clone();
+    let control_uid = args.uid.unwrap_or_else(|| unsafe { libc::getuid() });
+    let control_gid = args.gid.unwrap_or_else(|| unsafe { libc::getgid() });
+    let control_allow_root = args.allow_root;
+    let control_auto_unmount = args.auto_unmount;
+    let control_features = args.features.clone();
+
+    // Shared cell the FUSE INIT handler fills with the kernel-intersected
+    // capabilities: `crate::fuse::mount` calls `features.negotiate(&caps)` once
+    // the kernel replies and stores the JSON here, so the control socket
+    // reports what is truly live (not the requested set).
+    let negotiated: Arc<std::sync::Mutex<serde_json::Value>> =
+        Arc::new(std::sync::Mutex::new(serde_json::Value::Null));
 
     let fuse_opts = FuseMountOptions {
         mountpoint: args.mountpoint,
         auto_unmount: args.auto_unmount,
         allow_root: args.allow_root,
-        fsname,
+        fsname: fsname.clone(),
         uid: args.uid,
         gid: args.gid,
+        features: args.features.clone(),
+        negotiated: negotiated.clone(),
     };
 
     let mount = move || {
@@ -60,10 +189,12 @@ pub fn mount(args: MountArgs) -> Result<()> {
         let agentfs = rt.block_on(AgentFS::open(opts))?;
 
         // Check for overlay configuration
-        let fs: Arc<dyn FileSystem> = rt.block_on(async {
+        let (fs, overlay_base): (Arc<dyn FileSystem>, Option<String>) = rt.block_on(async {
             let conn = agentfs.get_connection();
 
-            // Check if fs_overlay_config table exists and has base_path
+            // Check if fs_overlay_config table exists and has base_path. The
+            // value may be a single path or a `lowerdir=a:b:c` style list of
+            // colon-separated read-only layers, highest-priority first.
             let query = "SELECT value FROM fs_overlay_config WHERE key = 'base_path'";
             let base_path: Option<String> = match conn.query(query, ()).await {
                 Ok(mut rows) => {
@@ -83,18 +214,76 @@ pub fn mount(args: MountArgs) -> Result<()> {
             };
 
             if let Some(base_path) = base_path {
-                // Create OverlayFS with HostFS base
-                eprintln!("Using overlay filesystem with base: {}", base_path);
-                let hostfs = HostFS::new(&base_path)?;
-                let overlay = OverlayFS::new(Arc::new(hostfs), agentfs.fs);
-                Ok::<Arc<dyn FileSystem>, anyhow::Error>(Arc::new(overlay))
+                // Split into ordered lower layers, skipping empty segments so a
+                // trailing colon doesn't yield a bogus "" path.
+                let lowers = parse_lower_layers(&base_path);
+
+                let mut layers: Vec<Arc<dyn FileSystem>> = Vec::with_capacity(lowers.len());
+                for lower in &lowers {
+                    layers.push(Arc::new(HostFS::new(lower)?) as Arc<dyn FileSystem>);
+                }
+
+                // Compose: agent upper first (writes/copy-up land here), then
+                // each lower in order; lookups stop at the first layer to hit.
+                eprintln!("Using overlay filesystem with lowers: {}", lowers.join(":"));
+                let overlay = OverlayFS::with_lowers(agentfs.fs, layers);
+                Ok::<(Arc<dyn FileSystem>, Option<String>), anyhow::Error>((
+                    Arc::new(overlay),
+                    Some(base_path),
+                ))
             } else {
                 // Plain AgentFS
-                Ok(Arc::new(agentfs.fs) as Arc<dyn FileSystem>)
+                Ok((Arc::new(agentfs.fs) as Arc<dyn FileSystem>, None))
             }
         })?;
 
-        crate::fuse::mount(fs, fuse_opts, rt)
+        // Serve the management control socket alongside the FUSE mount so a
+        // backgrounded daemon can be introspected and controlled by id.
+        let state = Arc::new(crate::control::ControlState {
+            mountpoint: control_mountpoint.clone(),
+            fsname: control_fsname,
+            uid: control_uid,
+            gid: control_gid,
+            overlay_base,
+            requested_features: serde_json::json!({
+                "writeback_cache": control_features.writeback_cache,
+                "splice_read": control_features.splice_read,
+                "splice_write": control_features.splice_write,
+                "readdirplus": control_features.readdirplus,
+                "parallel_dirops": control_features.parallel_dirops,
+                "max_readahead": control_features.max_readahead,
+                "max_write": control_features.max_write,
+                "min_abi": control_features.min_abi.map(|(maj, min)| format!("{}.{}", maj, min)),
+            }),
+            negotiated_features: negotiated.clone(),
+            allow_root: control_allow_root,
+            auto_unmount: control_auto_unmount,
+            started: std::time::Instant::now(),
+            is_mounted: Box::new({
+                let mp = control_mountpoint.clone();
+                move || is_mounted(&mp)
+            }),
+        });
+        let socket = crate::control::spawn(state, &control_id)?;
+
+        // Supervise SIGTERM: when a graceful shutdown is requested, drive the
+        // real unmount so the blocking FUSE session below returns and normal
+        // userspace teardown runs — independent of the kernel auto_unmount
+        // option. This is the same unmount path the control socket uses.
+        let watch_mountpoint = control_mountpoint.clone();
+        std::thread::spawn(move || loop {
+            if crate::daemon::shutdown_requested() {
+                let _ = crate::fuse::unmount(&watch_mountpoint);
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        });
+
+        let result = crate::fuse::mount(fs, fuse_opts, rt);
+
+        // Tie socket cleanup to the same teardown path as auto_unmount.
+        crate::control::remove_socket(&socket);
+        result
     };
 
     if args.foreground {
@@ -104,6 +293,8 @@ pub fn mount(args: MountArgs) -> Result<()> {
             mount,
             move || is_mounted(&mountpoint),
             std::time::Duration::from_secs(10),
+            args.private_namespace,
+            args.pid_file.clone(),
         )
     }
 }
@@ -117,6 +308,22 @@ pub fn mount(_args: MountArgs) -> Result<()> {
     );
 }
 
+/// Parse the `fs_overlay_config` base path into ordered lower layers.
+///
+/// The value is a colon-separated list modeled on the kernel's
+/// `lowerdir=a:b:c`, highest-priority first. Empty segments (e.g. from a
+/// trailing or doubled colon) are dropped and each path is trimmed, so the
+/// returned slice is exactly the layers to stack beneath the agent upper, in
+/// lookup order.
+#[cfg(target_os = "linux")]
+fn parse_lower_layers(base_path: &str) -> Vec<&str> {
+    base_path
+        .split(':')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
 /// Check if a path is a mountpoint by comparing device IDs
 #[cfg(target_os = "linux")]
 fn is_mounted(path: &std::path::Path) -> bool {
@@ -138,3 +345,76 @@ fn is_mounted(path: &std::path::Path) -> bool {
     // Different device IDs means it's a mountpoint
     path_meta.dev() != parent_meta.dev()
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_layers_preserve_order() {
+        assert_eq!(parse_lower_layers("/a:/b:/c"), vec!["/a", "/b", "/c"]);
+    }
+
+    #[test]
+    fn lower_layers_drop_empty_and_trim_segments() {
+        // A trailing colon, a doubled colon, and padding must not produce bogus
+        // "" layers or paths with surrounding whitespace.
+        assert_eq!(parse_lower_layers(" /a :: /b :"), vec!["/a", "/b"]);
+    }
+
+    #[test]
+    fn lower_layers_single_path() {
+        assert_eq!(parse_lower_layers("/only"), vec!["/only"]);
+    }
+
+    fn full_caps() -> KernelCaps {
+        KernelCaps {
+            writeback_cache: true,
+            splice_read: true,
+            splice_write: true,
+            readdirplus: true,
+            parallel_dirops: true,
+            max_readahead: 131072,
+            max_write: 131072,
+            abi: (7, 31),
+        }
+    }
+
+    #[test]
+    fn negotiate_drops_capabilities_the_kernel_lacks() {
+        let features = FuseFeatures {
+            writeback_cache: true,
+            readdirplus: true,
+            ..FuseFeatures::default()
+        };
+        let mut caps = full_caps();
+        caps.readdirplus = false;
+        let got = features.negotiate(&caps).unwrap();
+        // Requested and kernel-capable survives; requested-but-uncapable drops.
+        assert_eq!(got["writeback_cache"], serde_json::json!(true));
+        assert_eq!(got["readdirplus"], serde_json::json!(false));
+        // Never requested stays off.
+        assert_eq!(got["splice_read"], serde_json::json!(false));
+    }
+
+    #[test]
+    fn negotiate_clamps_sizes_to_kernel_maximum() {
+        let features = FuseFeatures {
+            max_readahead: Some(1 << 20),
+            max_write: Some(1 << 20),
+            ..FuseFeatures::default()
+        };
+        let got = features.negotiate(&full_caps()).unwrap();
+        assert_eq!(got["max_readahead"], serde_json::json!(131072));
+        assert_eq!(got["max_write"], serde_json::json!(131072));
+    }
+
+    #[test]
+    fn negotiate_rejects_kernel_below_required_abi() {
+        let features = FuseFeatures {
+            min_abi: Some((7, 36)),
+            ..FuseFeatures::default()
+        };
+        assert!(features.negotiate(&full_caps()).is_err());
+    }
+}
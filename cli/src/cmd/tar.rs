@@ -0,0 +1,568 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+#[cfg(target_os = "linux")]
+use anyhow::Context;
+#[cfg(target_os = "linux")]
+use std::io::{Read, Write};
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+
+#[cfg(target_os = "linux")]
+use agentfs_sdk::{AgentFS, AgentFSOptions, FileKind, FileSystem};
+
+/// Arguments for the `export` subcommand.
+#[derive(Debug, Clone)]
+pub struct ExportArgs {
+    /// The agent filesystem ID or path.
+    pub id_or_path: String,
+    /// Destination tar file; `None` streams to stdout.
+    pub output: Option<PathBuf>,
+}
+
+/// Arguments for the `import` subcommand.
+#[derive(Debug, Clone)]
+pub struct ImportArgs {
+    /// The agent filesystem ID or path to populate.
+    pub id_or_path: String,
+    /// Source tar file; `None` reads from stdin.
+    pub input: Option<PathBuf>,
+}
+
+/// Size of a POSIX tar block and of the streaming read/write chunks.
+#[cfg(target_os = "linux")]
+const BLOCK: usize = 512;
+#[cfg(target_os = "linux")]
+const CHUNK: usize = 64 * 1024;
+
+/// Serialize an agent filesystem to a POSIX (ustar) tar stream.
+///
+/// Walks the tree depth-first from the root, emitting a header for each entry
+/// (regular file, directory, or symlink) followed by file contents streamed in
+/// fixed [`CHUNK`]-sized reads so large files are never buffered whole.
+#[cfg(target_os = "linux")]
+pub fn export(args: ExportArgs) -> Result<()> {
+    let opts = AgentFSOptions::resolve(&args.id_or_path)?;
+    let rt = crate::get_runtime();
+    let agentfs = rt.block_on(AgentFS::open(opts))?;
+    let fs: Arc<dyn FileSystem> = Arc::new(agentfs.fs);
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout())),
+    };
+
+    rt.block_on(export_dir(&fs, "/", &mut out))?;
+
+    // Two zero blocks terminate a tar archive.
+    out.write_all(&[0u8; BLOCK * 2])?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Recursively emit the entries under `dir` (a "/"-rooted path) to `out`.
+#[cfg(target_os = "linux")]
+async fn export_dir(fs: &Arc<dyn FileSystem>, dir: &str, out: &mut dyn Write) -> Result<()> {
+    let mut entries = fs.readdir(dir).await?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for entry in entries {
+        if entry.name == "." || entry.name == ".." {
+            continue;
+        }
+        let path = join(dir, &entry.name);
+        let attr = fs.getattr(&path).await?;
+
+        match attr.kind {
+            FileKind::Directory => {
+                write_header(out, &path, &attr, TypeFlag::Dir, "")?;
+                Box::pin(export_dir(fs, &path, out)).await?;
+            }
+            FileKind::Symlink => {
+                let target = fs.readlink(&path).await?;
+                write_header(out, &path, &attr, TypeFlag::Symlink, &target)?;
+            }
+            FileKind::Regular => {
+                write_header(out, &path, &attr, TypeFlag::Regular, "")?;
+                stream_file(fs, &path, attr.size, out).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Stream `size` bytes of a regular file in [`CHUNK`]-sized reads, padding the
+/// final block to the 512-byte tar boundary.
+///
+/// The header (written by the caller) already declared `size`, so the payload
+/// must be exactly that many bytes or the archive would be misaligned with no
+/// error. If the file shrinks between `getattr` and the read — or a short read
+/// returns fewer bytes before `size` is reached — we bail rather than emit a
+/// corrupt entry.
+#[cfg(target_os = "linux")]
+async fn stream_file(
+    fs: &Arc<dyn FileSystem>,
+    path: &str,
+    size: u64,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut offset: u64 = 0;
+    while offset < size {
+        let want = std::cmp::min(CHUNK as u64, size - offset) as u32;
+        let data = fs.read(path, offset, want).await?;
+        if data.is_empty() {
+            break;
+        }
+        out.write_all(&data)?;
+        offset += data.len() as u64;
+    }
+
+    if offset != size {
+        anyhow::bail!(
+            "{}: read {} bytes but header declared {}; file changed during export",
+            path,
+            offset,
+            size
+        );
+    }
+
+    let rem = (size as usize) % BLOCK;
+    if rem != 0 {
+        out.write_all(&vec![0u8; BLOCK - rem])?;
+    }
+    Ok(())
+}
+
+/// Reconstruct an agent filesystem from a POSIX tar stream.
+///
+/// Reads headers sequentially and replays each as the matching [`FileSystem`]
+/// operation: `mkdir` for directories, `symlink` for links, and
+/// `create` + chunked `write` for regular files.
+#[cfg(target_os = "linux")]
+pub fn import(args: ImportArgs) -> Result<()> {
+    let opts = AgentFSOptions::resolve(&args.id_or_path)?;
+    let rt = crate::get_runtime();
+    let agentfs = rt.block_on(AgentFS::open(opts))?;
+    let fs: Arc<dyn FileSystem> = Arc::new(agentfs.fs);
+
+    let mut input: Box<dyn Read> = match &args.input {
+        Some(path) => Box::new(std::io::BufReader::new(
+            std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufReader::new(std::io::stdin())),
+    };
+
+    rt.block_on(import_stream(&fs, &mut input))
+}
+
+/// Drive the import replay loop over a tar stream.
+#[cfg(target_os = "linux")]
+async fn import_stream(fs: &Arc<dyn FileSystem>, input: &mut dyn Read) -> Result<()> {
+    let mut block = [0u8; BLOCK];
+    loop {
+        if !read_full(input, &mut block)? {
+            break; // clean EOF at a block boundary
+        }
+        if block.iter().all(|&b| b == 0) {
+            break; // end-of-archive marker
+        }
+
+        let header = Header::parse(&block)?;
+        let path = normalize(&header.name);
+
+        match header.typeflag {
+            TypeFlag::Dir => {
+                fs.mkdir(&path, header.mode).await?;
+            }
+            TypeFlag::Symlink => {
+                fs.symlink(&header.linkname, &path).await?;
+            }
+            TypeFlag::Regular => {
+                fs.create(&path, header.mode).await?;
+                copy_into(fs, &path, header.size, input).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read exactly `header.size` bytes of file payload from the stream and write
+/// them into the new file in [`CHUNK`]-sized pieces, consuming the trailing
+/// block padding.
+#[cfg(target_os = "linux")]
+async fn copy_into(
+    fs: &Arc<dyn FileSystem>,
+    path: &str,
+    size: u64,
+    input: &mut dyn Read,
+) -> Result<()> {
+    let mut remaining = size;
+    let mut offset: u64 = 0;
+    let mut buf = vec![0u8; CHUNK];
+    while remaining > 0 {
+        let want = std::cmp::min(CHUNK as u64, remaining) as usize;
+        input.read_exact(&mut buf[..want])?;
+        fs.write(path, offset, &buf[..want]).await?;
+        offset += want as u64;
+        remaining -= want as u64;
+    }
+
+    let pad = (BLOCK - (size as usize % BLOCK)) % BLOCK;
+    if pad != 0 {
+        let mut sink = vec![0u8; pad];
+        input.read_exact(&mut sink)?;
+    }
+    Ok(())
+}
+
+/// tar entry type, restricted to the kinds an agent filesystem produces.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypeFlag {
+    Regular,
+    Dir,
+    Symlink,
+}
+
+#[cfg(target_os = "linux")]
+impl TypeFlag {
+    fn byte(self) -> u8 {
+        match self {
+            TypeFlag::Regular => b'0',
+            TypeFlag::Dir => b'5',
+            TypeFlag::Symlink => b'2',
+        }
+    }
+
+    fn from_byte(b: u8) -> Result<Self> {
+        match b {
+            b'0' | b'\0' => Ok(TypeFlag::Regular),
+            b'5' => Ok(TypeFlag::Dir),
+            b'2' => Ok(TypeFlag::Symlink),
+            other => anyhow::bail!("unsupported tar type flag: {}", other as char),
+        }
+    }
+}
+
+/// A parsed ustar header (only the fields we replay on import).
+#[cfg(target_os = "linux")]
+struct Header {
+    name: String,
+    mode: u32,
+    size: u64,
+    typeflag: TypeFlag,
+    linkname: String,
+}
+
+#[cfg(target_os = "linux")]
+impl Header {
+    fn parse(block: &[u8; BLOCK]) -> Result<Self> {
+        let name = cstr(&block[0..100]);
+        let prefix = cstr(&block[345..500]);
+        let name = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        let mode = octal(&block[100..108])? as u32;
+        let size = octal(&block[124..136])?;
+        let typeflag = TypeFlag::from_byte(block[156])?;
+        let linkname = cstr(&block[157..257]);
+        Ok(Header {
+            name,
+            mode,
+            size,
+            typeflag,
+            linkname,
+        })
+    }
+}
+
+/// Write a ustar header block for one entry.
+#[cfg(target_os = "linux")]
+fn write_header(
+    out: &mut dyn Write,
+    path: &str,
+    attr: &agentfs_sdk::FileAttr,
+    typeflag: TypeFlag,
+    linkname: &str,
+) -> Result<()> {
+    let mut block = [0u8; BLOCK];
+
+    // Directories carry a trailing slash per the ustar convention.
+    let name = path.trim_start_matches('/');
+    let name = if typeflag == TypeFlag::Dir {
+        format!("{}/", name)
+    } else {
+        name.to_string()
+    };
+
+    // ustar stores the path across a 100-byte `name` and a 155-byte `prefix`
+    // field split on a `/` boundary; `Header::parse` already rejoins them, so
+    // write both here rather than bailing on everything over 100 bytes.
+    let (prefix, name) = split_ustar_path(&name)?;
+    write_str(&mut block[0..100], name);
+    if !prefix.is_empty() {
+        write_str(&mut block[345..500], prefix);
+    }
+    write_octal(&mut block[100..108], attr.mode as u64, 7)?;
+    write_octal(&mut block[108..116], attr.uid as u64, 7)?;
+    write_octal(&mut block[116..124], attr.gid as u64, 7)?;
+    let size = if typeflag == TypeFlag::Regular {
+        attr.size
+    } else {
+        0
+    };
+    write_octal(&mut block[124..136], size, 11)?;
+    write_octal(&mut block[136..148], attr.mtime, 11)?;
+    block[156] = typeflag.byte();
+    if typeflag == TypeFlag::Symlink {
+        write_str(&mut block[157..257], linkname);
+    }
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with the checksum field filled with spaces.
+    for b in &mut block[148..156] {
+        *b = b' ';
+    }
+    let sum: u32 = block.iter().map(|&b| b as u32).sum();
+    write_octal(&mut block[148..155], sum as u64, 6)?;
+    block[155] = b' ';
+
+    out.write_all(&block)?;
+    Ok(())
+}
+
+/// Join a "/"-rooted directory with a child name without doubling slashes.
+#[cfg(target_os = "linux")]
+fn join(dir: &str, name: &str) -> String {
+    if dir == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Split a path into the ustar `(prefix, name)` pair.
+///
+/// `name` must fit in 100 bytes and `prefix` in 155; the split happens on a
+/// `/` boundary so the reader can rejoin them with a single `/`. Returns an
+/// error only when no split makes both halves fit (e.g. a single path
+/// component longer than 100 bytes).
+#[cfg(target_os = "linux")]
+fn split_ustar_path(path: &str) -> Result<(&str, &str)> {
+    if path.len() <= 100 {
+        return Ok(("", path));
+    }
+
+    // Prefer the rightmost `/` that leaves the name within 100 bytes and the
+    // prefix within 155, matching GNU tar's behavior.
+    let split = path
+        .char_indices()
+        .filter(|&(i, c)| c == '/' && path.len() - i - 1 <= 100 && i <= 155)
+        .map(|(i, _)| i)
+        .next_back();
+
+    match split {
+        Some(i) => Ok((&path[..i], &path[i + 1..])),
+        None => anyhow::bail!("path too long for ustar (no valid name/prefix split): {}", path),
+    }
+}
+
+/// Turn an archived (relative) path into a "/"-rooted filesystem path.
+#[cfg(target_os = "linux")]
+fn normalize(name: &str) -> String {
+    format!("/{}", name.trim_matches('/'))
+}
+
+/// Read a NUL-terminated ASCII field into an owned string.
+#[cfg(target_os = "linux")]
+fn cstr(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Parse a space/NUL-padded octal field.
+#[cfg(target_os = "linux")]
+fn octal(field: &[u8]) -> Result<u64> {
+    let s: String = field
+        .iter()
+        .map(|&b| b as char)
+        .filter(|c| c.is_ascii_digit())
+        .collect();
+    if s.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(&s, 8).context("invalid octal field in tar header")
+}
+
+/// Write a string into a fixed field, left-aligned and NUL-padded.
+#[cfg(target_os = "linux")]
+fn write_str(field: &mut [u8], s: &str) {
+    let bytes = s.as_bytes();
+    let n = std::cmp::min(bytes.len(), field.len());
+    field[..n].copy_from_slice(&bytes[..n]);
+}
+
+/// Write a zero-padded octal number of `width` digits followed by a NUL.
+///
+/// Errors if `value` does not fit in `width` octal digits: truncating to the
+/// low digits would silently corrupt the field (e.g. a size field for a file
+/// of 8 GiB or more), so we refuse instead.
+#[cfg(target_os = "linux")]
+fn write_octal(field: &mut [u8], value: u64, width: usize) -> Result<()> {
+    let s = format!("{:0>width$o}", value, width = width);
+    if s.len() > width {
+        anyhow::bail!("value {} does not fit in {} octal digits", value, width);
+    }
+    let bytes = s.as_bytes();
+    field[..width].copy_from_slice(bytes);
+    if width < field.len() {
+        field[width] = 0;
+    }
+    Ok(())
+}
+
+/// Read exactly `buf.len()` bytes, returning `false` on clean EOF before any
+/// byte is read (so the caller can stop at a block boundary).
+#[cfg(target_os = "linux")]
+fn read_full(input: &mut dyn Read, buf: &mut [u8]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => anyhow::bail!("unexpected EOF in tar stream"),
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+/// Export/import are Linux-only, mirroring `mount`.
+#[cfg(target_os = "macos")]
+pub fn export(_args: ExportArgs) -> Result<()> {
+    anyhow::bail!("tar export is not supported on macOS in this version.");
+}
+
+#[cfg(target_os = "macos")]
+pub fn import(_args: ImportArgs) -> Result<()> {
+    anyhow::bail!("tar import is not supported on macOS in this version.");
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use agentfs_sdk::{FileAttr, FileKind};
+
+    fn attr(kind: FileKind, mode: u32, size: u64) -> FileAttr {
+        FileAttr {
+            kind,
+            mode,
+            uid: 1000,
+            gid: 1000,
+            size,
+            mtime: 0o1234567,
+        }
+    }
+
+    /// Encode an entry and return its header block.
+    fn encode(path: &str, a: &FileAttr, flag: TypeFlag, link: &str) -> [u8; BLOCK] {
+        let mut buf = Vec::new();
+        write_header(&mut buf, path, a, flag, link).unwrap();
+        let mut block = [0u8; BLOCK];
+        block.copy_from_slice(&buf[..BLOCK]);
+        block
+    }
+
+    /// The ustar checksum stored in a header must equal the sum of all bytes
+    /// with the checksum field taken as spaces.
+    fn assert_checksum(block: &[u8; BLOCK]) {
+        let mut scratch = *block;
+        for b in &mut scratch[148..156] {
+            *b = b' ';
+        }
+        let expected: u64 = scratch.iter().map(|&b| b as u64).sum();
+        assert_eq!(octal(&block[148..156]).unwrap(), expected);
+    }
+
+    #[test]
+    fn regular_file_roundtrips() {
+        // A deliberately non-512-aligned size; the padding it needs is checked
+        // at the end.
+        let a = attr(FileKind::Regular, 0o644, 1000);
+        let block = encode("dir/file.txt", &a, TypeFlag::Regular, "");
+        assert_checksum(&block);
+
+        let h = Header::parse(&block).unwrap();
+        assert_eq!(h.name, "dir/file.txt");
+        assert_eq!(h.mode, 0o644);
+        assert_eq!(h.size, 1000);
+        assert_eq!(h.typeflag, TypeFlag::Regular);
+
+        // 1000 bytes occupy two blocks with 24 bytes of trailing padding.
+        assert_eq!((BLOCK - (1000 % BLOCK)) % BLOCK, 24);
+    }
+
+    #[test]
+    fn directory_roundtrips() {
+        let a = attr(FileKind::Directory, 0o755, 0);
+        let block = encode("dir", &a, TypeFlag::Dir, "");
+        assert_checksum(&block);
+
+        let h = Header::parse(&block).unwrap();
+        // Directories are stored with a trailing slash and carry no payload.
+        assert_eq!(h.name, "dir/");
+        assert_eq!(h.typeflag, TypeFlag::Dir);
+        assert_eq!(h.size, 0);
+    }
+
+    #[test]
+    fn symlink_roundtrips() {
+        let a = attr(FileKind::Symlink, 0o777, 0);
+        let block = encode("dir/link", &a, TypeFlag::Symlink, "../target");
+        assert_checksum(&block);
+
+        let h = Header::parse(&block).unwrap();
+        assert_eq!(h.name, "dir/link");
+        assert_eq!(h.typeflag, TypeFlag::Symlink);
+        assert_eq!(h.linkname, "../target");
+    }
+
+    #[test]
+    fn long_path_uses_name_prefix_split() {
+        // A path longer than the 100-byte name field must round-trip through
+        // the ustar prefix field rather than being rejected.
+        let deep = format!("{}/file.txt", vec!["dir"; 40].join("/"));
+        assert!(deep.len() > 100);
+
+        let a = attr(FileKind::Regular, 0o644, 0);
+        let block = encode(&deep, &a, TypeFlag::Regular, "");
+        assert_checksum(&block);
+
+        let h = Header::parse(&block).unwrap();
+        assert_eq!(h.name, deep);
+    }
+
+    #[test]
+    fn oversized_single_component_is_rejected() {
+        // No `/` split can make a single 200-byte component fit.
+        let huge = "a".repeat(200);
+        assert!(split_ustar_path(&huge).is_err());
+    }
+
+    #[test]
+    fn octal_roundtrips_and_rejects_overflow() {
+        let mut field = [0u8; 12];
+        write_octal(&mut field, 0o1234567, 11).unwrap();
+        assert_eq!(octal(&field).unwrap(), 0o1234567);
+
+        // A value needing more than `width` octal digits must error, not
+        // silently truncate to the low digits.
+        let mut small = [0u8; 12];
+        assert!(write_octal(&mut small, 8 * 1024 * 1024 * 1024, 7).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Shared state the control handler reports over the socket.
+///
+/// A single instance is created in the `mount` closure and handed to the
+/// control thread; the mount path itself is used both for `describe` and to
+/// drive the `unmount` operation through [`crate::fuse`]. `allow_root` and
+/// `auto_unmount` are recorded as the values the live FUSE session was mounted
+/// with — they are fixed for the lifetime of the mount and cannot be changed
+/// without remounting.
+pub struct ControlState {
+    pub mountpoint: PathBuf,
+    pub fsname: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub overlay_base: Option<String>,
+    /// The feature set requested at mount time, before kernel negotiation.
+    pub requested_features: serde_json::Value,
+    /// The kernel-intersected feature set, filled by `crate::fuse::mount` at
+    /// INIT; `Null` until negotiation completes.
+    pub negotiated_features: Arc<Mutex<serde_json::Value>>,
+    pub allow_root: bool,
+    pub auto_unmount: bool,
+    pub started: Instant,
+    /// Polled for the `is_mounted` field of `describe`.
+    pub is_mounted: Box<dyn Fn() -> bool + Send + Sync>,
+}
+
+/// Resolve the control socket path for a given agent id.
+///
+/// Uses `$XDG_RUNTIME_DIR/agentfs/<id>.sock`, falling back to `/tmp` when the
+/// runtime dir is not set. The `agentfs` subdirectory is created if missing.
+pub fn socket_path(id: &str) -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    let dir = base.join("agentfs");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{}.sock", sanitize(id))))
+}
+
+/// Keep a socket filename filesystem-safe by collapsing path separators.
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| if c == '/' || c == '\\' { '-' } else { c })
+        .collect()
+}
+
+/// Serve newline-delimited JSON control requests until accepting a connection
+/// fails; unlinking the socket file does not stop the loop.
+///
+/// Binds `socket_path`, unlinking any stale socket first, and dispatches each
+/// line to [`handle_request`]. The server runs for the lifetime of the daemon
+/// thread; the socket file itself is unlinked by the mount closure during
+/// teardown (the same cleanup that handles auto-unmount).
+pub fn serve(state: Arc<ControlState>, socket_path: PathBuf) -> Result<()> {
+    // A stale socket from a crashed daemon would otherwise block bind().
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path)?;
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(&state, stream) {
+                    eprintln!("control: connection error: {}", e);
+                }
+            }
+            Err(e) => {
+                eprintln!("control: accept error: {}", e);
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read requests from a single client connection, one JSON object per line.
+fn handle_connection(state: &Arc<ControlState>, stream: UnixStream) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = handle_request(state, &line);
+        writer.write_all(reply.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Dispatch one request line and return the JSON response body.
+fn handle_request(state: &Arc<ControlState>, line: &str) -> String {
+    let req: serde_json::Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => return error_reply(&format!("invalid JSON: {}", e)),
+    };
+
+    match req.get("op").and_then(|v| v.as_str()) {
+        Some("describe") => describe(state),
+        Some("configure") => configure(state, &req),
+        Some("unmount") => unmount(state),
+        Some(other) => error_reply(&format!("unknown op: {}", other)),
+        None => error_reply("missing \"op\" field"),
+    }
+}
+
+/// Report the daemon's current mount identity and runtime state.
+fn describe(state: &Arc<ControlState>) -> String {
+    let body = serde_json::json!({
+        "ok": true,
+        "mountpoint": state.mountpoint.to_string_lossy(),
+        "fsname": state.fsname,
+        "uid": state.uid,
+        "gid": state.gid,
+        "overlay_base": state.overlay_base,
+        "requested_features": state.requested_features,
+        "negotiated_features": state.negotiated_features.lock().unwrap().clone(),
+        "is_mounted": (state.is_mounted)(),
+        "allow_root": state.allow_root,
+        "auto_unmount": state.auto_unmount,
+        "uptime_secs": state.started.elapsed().as_secs(),
+    });
+    body.to_string()
+}
+
+/// Reject live reconfiguration.
+///
+/// `allow_root` and `auto_unmount` are FUSE mount options captured by the
+/// kernel session at mount time; they cannot be changed without remounting, so
+/// rather than silently pretend a toggle took effect we report it explicitly.
+fn configure(_state: &Arc<ControlState>, _req: &serde_json::Value) -> String {
+    error_reply("not live-changeable: allow_root/auto_unmount are fixed at mount time; remount to change them")
+}
+
+/// Gracefully tear the mount down via [`crate::fuse`]. The control server
+/// itself keeps accepting connections afterwards.
+fn unmount(state: &Arc<ControlState>) -> String {
+    match crate::fuse::unmount(&state.mountpoint) {
+        Ok(()) => serde_json::json!({ "ok": true, "unmounted": true }).to_string(),
+        Err(e) => error_reply(&format!("unmount failed: {}", e)),
+    }
+}
+
+/// Format a uniform error response.
+fn error_reply(msg: &str) -> String {
+    serde_json::json!({ "ok": false, "error": msg }).to_string()
+}
+
+/// Spawn the control server on its own thread for the lifetime of the daemon.
+///
+/// Returns the bound socket path so the caller can unlink it as part of the
+/// same cleanup that handles auto-unmount.
+pub fn spawn(state: Arc<ControlState>, id: &str) -> Result<PathBuf> {
+    let path = socket_path(id)?;
+    let thread_path = path.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = serve(state, thread_path) {
+            eprintln!("control: server exited: {}", e);
+        }
+    });
+    Ok(path)
+}
+
+/// Remove the control socket at `path` if it exists (cleanup helper).
+pub fn remove_socket(path: &Path) {
+    let _ = std::fs::remove_file(path);
+}
@@ -1,6 +1,22 @@
 use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
+/// Set by the SIGTERM handler; polled by the mount supervisory loop, which
+/// drives the real `crate::fuse` unmount so teardown does not depend on the
+/// kernel `auto_unmount` option being set.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a graceful shutdown (SIGTERM) has been requested.
+///
+/// The mount supervisory loop polls this and, when set, unmounts via
+/// [`crate::fuse::unmount`]; that returns control from the blocking FUSE
+/// session so the normal userspace teardown runs.
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::Relaxed)
+}
+
 /// Daemonize the current process and run a function in the daemon.
 ///
 /// This function forks the process, detaches from the terminal, and runs the
@@ -11,11 +27,22 @@ use std::time::Duration;
 /// * `daemon_fn` - The function to run in the daemon process (should block until done)
 /// * `ready_check` - A function that polls for readiness (returns true when ready)
 /// * `timeout` - How long to wait for the ready_check to succeed
+/// * `private_namespace` - Unshare a private mount namespace in the child before
+///   running `daemon_fn`, so the mount is invisible to the parent namespace
+/// * `pid_file` - Optional path the child writes its PID to once ready; the
+///   parent only returns `Ok(())` after the file appears. The file is unlinked
+///   on a clean exit and on SIGTERM.
 ///
 /// # Returns
 /// * `Ok(())` in the parent process if the daemon started successfully
 /// * Never returns in the child process (exits with appropriate code)
-pub fn daemonize<F, R>(daemon_fn: F, ready_check: R, timeout: Duration) -> Result<()>
+pub fn daemonize<F, R>(
+    daemon_fn: F,
+    ready_check: R,
+    timeout: Duration,
+    private_namespace: bool,
+    pid_file: Option<PathBuf>,
+) -> Result<()>
 where
     F: FnOnce() -> Result<()> + Send + 'static,
     R: Fn() -> bool,
@@ -48,12 +75,22 @@ where
             // Redirect stdin/stdout/stderr to /dev/null
             redirect_stdio_to_devnull();
 
+            // Enter a private mount namespace before the daemon thread is
+            // spawned, so the thread that performs the FUSE mount inherits it
+            // and the mount never propagates back to the parent namespace.
+            if private_namespace {
+                if let Err(_e) = enter_private_namespace() {
+                    let _ = signal_parent(write_fd, false);
+                    std::process::exit(1);
+                }
+            }
+
             // Run the daemon function in a separate thread
             let daemon_thread = std::thread::spawn(daemon_fn);
 
             // Wait for readiness, but fail early if daemon thread exits
             let start = std::time::Instant::now();
-            let ready = loop {
+            let mut ready = loop {
                 if ready_check() {
                     break true;
                 }
@@ -66,6 +103,19 @@ where
                 std::thread::sleep(Duration::from_millis(50));
             };
 
+            // Write the PID file and arm signal handling before acknowledging
+            // readiness, so the parent never observes a ready daemon without a
+            // PID file, and SIGTERM is handled for the whole ready lifetime.
+            if ready {
+                if let Some(path) = &pid_file {
+                    if write_pid_file(path).is_err() {
+                        ready = false;
+                    } else {
+                        install_signal_handlers();
+                    }
+                }
+            }
+
             // Signal parent
             let _ = signal_parent(write_fd, ready);
             unsafe { libc::close(write_fd) };
@@ -75,10 +125,14 @@ where
             }
 
             // Wait for daemon thread (blocks until done)
-            match daemon_thread.join() {
-                Ok(Ok(())) => std::process::exit(0),
-                _ => std::process::exit(1),
+            let code = match daemon_thread.join() {
+                Ok(Ok(())) => 0,
+                _ => 1,
+            };
+            if let Some(path) = &pid_file {
+                let _ = std::fs::remove_file(path);
             }
+            std::process::exit(code);
         }
         _child_pid => {
             // Parent process
@@ -88,11 +142,24 @@ where
             let success = wait_for_signal(read_fd);
             unsafe { libc::close(read_fd) };
 
-            if success {
-                Ok(())
-            } else {
-                anyhow::bail!("Daemon failed to start")
+            if !success {
+                anyhow::bail!("Daemon failed to start");
             }
+
+            // The child writes the PID file just before acknowledging
+            // readiness; wait for it to appear so callers (and `stop`) can rely
+            // on it the moment `daemonize` returns.
+            if let Some(path) = &pid_file {
+                let start = std::time::Instant::now();
+                while !path.exists() {
+                    if start.elapsed() >= timeout {
+                        anyhow::bail!("Daemon started but PID file never appeared");
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+            }
+
+            Ok(())
         }
     }
 }
@@ -145,6 +212,109 @@ fn wait_for_signal(fd: libc::c_int) -> bool {
     }
 }
 
+/// Enter a private mount namespace for the calling thread.
+///
+/// Unshares `CLONE_NEWNS` and then remounts the root recursively as
+/// `MS_PRIVATE` so that mounts performed afterwards (the FUSE mount) are not
+/// propagated back to the parent namespace. Threads spawned after this call
+/// inherit the unshared namespace.
+fn enter_private_namespace() -> Result<()> {
+    if unsafe { libc::unshare(libc::CLONE_NEWNS) } != 0 {
+        anyhow::bail!(
+            "unshare(CLONE_NEWNS) failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    // mount(NULL, "/", NULL, MS_REC | MS_PRIVATE, NULL)
+    let root = c"/";
+    let ret = unsafe {
+        libc::mount(
+            std::ptr::null(),
+            root.as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        )
+    };
+    if ret != 0 {
+        anyhow::bail!(
+            "mount(/, MS_REC|MS_PRIVATE) failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    Ok(())
+}
+
+/// Write the current PID to `path`, creating parent directories as needed.
+fn write_pid_file(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    std::fs::write(path, format!("{}\n", unsafe { libc::getpid() }))?;
+    Ok(())
+}
+
+/// Install the SIGTERM handler for graceful shutdown.
+///
+/// The handler only flips an atomic flag (async-signal-safe); the mount
+/// supervisory loop observes it via [`shutdown_requested`] and performs the
+/// real unmount, so teardown runs userspace `Drop` regardless of whether the
+/// kernel `auto_unmount` option is set.
+fn install_signal_handlers() {
+    unsafe {
+        libc::signal(libc::SIGTERM, handle_sigterm as libc::sighandler_t);
+    }
+}
+
+/// SIGTERM handler: request a graceful shutdown. Async-signal-safe.
+extern "C" fn handle_sigterm(_sig: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Read and parse a PID from a PID file.
+fn read_pid_file(path: &Path) -> Result<libc::pid_t> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read PID file {}: {}", path.display(), e))?;
+    contents
+        .trim()
+        .parse::<libc::pid_t>()
+        .map_err(|_| anyhow::anyhow!("PID file {} does not contain a valid PID", path.display()))
+}
+
+/// Return whether `pid` refers to a live process (`kill(pid, 0)`).
+fn process_alive(pid: libc::pid_t) -> bool {
+    unsafe { libc::kill(pid, 0) == 0 }
+}
+
+/// Deliver `signal` to the daemon named by `pid_file`, after verifying it is
+/// alive. A stale PID file (dead process) is removed and reported as an error.
+fn signal_daemon(pid_file: &Path, signal: libc::c_int) -> Result<libc::pid_t> {
+    let pid = read_pid_file(pid_file)?;
+    if !process_alive(pid) {
+        let _ = std::fs::remove_file(pid_file);
+        anyhow::bail!("Daemon (pid {}) is not running; removed stale PID file", pid);
+    }
+    if unsafe { libc::kill(pid, signal) } != 0 {
+        anyhow::bail!(
+            "Failed to signal daemon (pid {}): {}",
+            pid,
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(pid)
+}
+
+/// Stop a running daemon: send SIGTERM for a graceful unmount-and-exit.
+pub fn stop(pid_file: &Path) -> Result<()> {
+    let pid = signal_daemon(pid_file, libc::SIGTERM)?;
+    eprintln!("Sent SIGTERM to daemon (pid {})", pid);
+    Ok(())
+}
+
 /// Redirect stdio to /dev/null for daemon
 fn redirect_stdio_to_devnull() {
     unsafe {